@@ -9,7 +9,7 @@ Building a native binary containing Python.
 use {
     crate::{environment::Environment, py_packaging::distribution::AppleSdkInfo},
     anyhow::{anyhow, Context, Result},
-    python_packaging::libpython::LibPythonBuildContext,
+    python_packaging::libpython::{InterpreterKind, LibPythonBuildContext},
     slog::warn,
     std::{
         fs,
@@ -20,13 +20,40 @@ use {
 };
 
 /// Produce the content of the config.c file containing built-in extensions.
-pub fn make_config_c<T>(extensions: &[(T, T)]) -> String
+///
+/// `abi3_minor` is the minor version floor (e.g. `8` for 3.8) of the CPython
+/// stable ABI the embedded interpreter should be built against. When set,
+/// `Py_LIMITED_API` is defined ahead of the `Python.h` include. The entries of
+/// `extensions` are emitted as given either way: init functions are not
+/// filtered down to those in the limited API, and the `_PyImport_Inittab`
+/// table is always emitted.
+///
+/// PyPy does not use a `config.c`-style `_inittab` to wire up built-in
+/// extensions, so `interpreter_kind` of [InterpreterKind::PyPy] short-circuits
+/// to an empty translation unit.
+pub fn make_config_c<T>(
+    extensions: &[(T, T)],
+    interpreter_kind: InterpreterKind,
+    abi3_minor: Option<u8>,
+) -> String
 where
     T: AsRef<str>,
 {
+    if interpreter_kind == InterpreterKind::PyPy {
+        return "/* PyPy does not use a config.c-style inittab. */".to_string();
+    }
+
     // It is easier to construct the file from scratch than parse the template
     // and insert things in the right places.
-    let mut lines: Vec<String> = vec!["#include \"Python.h\"".to_string()];
+    let mut lines: Vec<String> = Vec::new();
+
+    // Py_LIMITED_API must be defined before Python.h is included for it to take
+    // effect.
+    if let Some(minor) = abi3_minor {
+        lines.push(format!("#define Py_LIMITED_API 0x03{:02x}0000", minor));
+    }
+
+    lines.push(String::from("#include \"Python.h\""));
 
     // Declare the initialization functions.
     for (_name, init_fn) in extensions {
@@ -47,6 +74,20 @@ where
     lines.join("\n")
 }
 
+/// Resolve the base name of the static runtime library to produce/link against.
+fn runtime_library_name(interpreter_kind: InterpreterKind, abi3_minor: Option<u8>) -> &'static str {
+    match interpreter_kind {
+        InterpreterKind::PyPy => "pypy3-c",
+        InterpreterKind::CPython => {
+            if abi3_minor.is_some() {
+                "python3"
+            } else {
+                "pythonXY"
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LibpythonInfo {
     pub libpython_path: PathBuf,
@@ -54,8 +95,204 @@ pub struct LibpythonInfo {
     pub cargo_metadata: Vec<String>,
 }
 
+/// Merge a set of static archives into a single Apple universal (fat) archive.
+///
+/// `fat_macho::FatWriter` parses its inputs as Mach-O object/binary files, but
+/// our inputs are `ar` static archives (`libpythonXY.a`), which aren't Mach-O.
+/// We shell out to the `lipo` tool instead, which understands how to merge
+/// static archives into a fat archive directly.
+fn lipo_merge(inputs: &[&Path], output: &Path) -> Result<()> {
+    let status = std::process::Command::new("lipo")
+        .arg("-create")
+        .args(inputs)
+        .arg("-output")
+        .arg(output)
+        .status()
+        .context("running lipo")?;
+
+    if !status.success() {
+        return Err(anyhow!("lipo -create exited with {}", status));
+    }
+
+    Ok(())
+}
+
+/// Create a universal2 (fat) static libpython for macOS by building and merging
+/// an `x86_64-apple-darwin` and an `aarch64-apple-darwin` slice.
+///
+/// The two contexts must be distinct: `object_files` and other per-arch inputs
+/// differ between the two target triples, even though both binaries ultimately
+/// embed the same Python distribution version.
+#[allow(clippy::too_many_arguments)]
+pub fn link_libpython_universal2(
+    logger: &slog::Logger,
+    env: &Environment,
+    x86_64_context: &LibPythonBuildContext,
+    aarch64_context: &LibPythonBuildContext,
+    out_dir: &Path,
+    host_triple: &str,
+    opt_level: &str,
+    apple_sdk_info: &AppleSdkInfo,
+) -> Result<LibpythonInfo> {
+    let x86_64_dir = out_dir.join("universal2-x86_64-apple-darwin");
+    let aarch64_dir = out_dir.join("universal2-aarch64-apple-darwin");
+    create_dir_all(&x86_64_dir)?;
+    create_dir_all(&aarch64_dir)?;
+
+    warn!(logger, "building x86_64-apple-darwin slice of universal2 libpython");
+    let x86_64_info = link_libpython(
+        logger,
+        env,
+        x86_64_context,
+        &x86_64_dir,
+        host_triple,
+        "x86_64-apple-darwin",
+        opt_level,
+        Some(apple_sdk_info),
+        None,
+    )?;
+
+    warn!(logger, "building aarch64-apple-darwin slice of universal2 libpython");
+    let aarch64_info = link_libpython(
+        logger,
+        env,
+        aarch64_context,
+        &aarch64_dir,
+        host_triple,
+        "aarch64-apple-darwin",
+        opt_level,
+        Some(apple_sdk_info),
+        None,
+    )?;
+
+    let python_lib_name =
+        runtime_library_name(x86_64_context.interpreter_kind, x86_64_context.abi3_minor);
+
+    warn!(logger, "merging slices into a universal2 lib{}.a", python_lib_name);
+    let libpython_path = out_dir.join(format!("lib{}.a", python_lib_name));
+    lipo_merge(
+        &[&x86_64_info.libpython_path, &aarch64_info.libpython_path],
+        &libpython_path,
+    )?;
+
+    let libpyembeddedconfig_path = out_dir.join("libpyembeddedconfig.a");
+    lipo_merge(
+        &[
+            &x86_64_info.libpyembeddedconfig_path,
+            &aarch64_info.libpyembeddedconfig_path,
+        ],
+        &libpyembeddedconfig_path,
+    )?;
+
+    // The two slices share identical `rustc-link-lib`/`rustc-link-search` metadata
+    // (frameworks, system libraries, clang_rt) aside from the lines naming the
+    // per-arch archives we just merged and the per-arch out dirs, which we already
+    // emit below pointing at the merged archives instead. `link_libpython`'s
+    // `minimize_link_search_paths` canonicalizes search directories before
+    // emitting them (e.g. macOS resolves `/var` to `/private/var`), so we must
+    // compare against the canonicalized `x86_64_dir`, not the path we passed in,
+    // or the stale per-arch (thin) search directory survives ahead of the merged
+    // `out_dir` and the linker picks the wrong, non-universal archive.
+    let x86_64_dir_canonical = x86_64_dir
+        .canonicalize()
+        .unwrap_or_else(|_| x86_64_dir.clone());
+    let x86_64_dir_search_line = format!(
+        "cargo:rustc-link-search=native={}",
+        x86_64_dir_canonical.display()
+    );
+
+    let mut cargo_metadata: Vec<String> = x86_64_info
+        .cargo_metadata
+        .into_iter()
+        .filter(|line| {
+            line != &format!("cargo:rustc-link-lib=static={}", python_lib_name)
+                && line != "cargo:rustc-link-lib=static=pyembeddedconfig"
+                && line != &x86_64_dir_search_line
+        })
+        .collect();
+
+    cargo_metadata.push("cargo:rustc-link-lib=static=pyembeddedconfig".to_string());
+    cargo_metadata.push(format!("cargo:rustc-link-lib=static={}", python_lib_name));
+    cargo_metadata.push(format!(
+        "cargo:rustc-link-search=native={}",
+        out_dir.display()
+    ));
+
+    Ok(LibpythonInfo {
+        libpython_path,
+        libpyembeddedconfig_path,
+        cargo_metadata,
+    })
+}
+
+/// A description of a target Python interpreter's ABI, serialized as text.
+///
+/// This mirrors the `key = value` config file format pyo3-build-config accepts
+/// via `PYO3_CONFIG_FILE`. It lets [link_libpython] emit correct linker metadata
+/// for a target whose interpreter cannot be probed directly from the host,
+/// such as when cross-compiling from an x86-64 Linux host to Windows or
+/// ARM Linux.
+#[derive(Clone, Debug, Default)]
+pub struct InterpreterConfig {
+    pub version: String,
+    pub implementation: String,
+    pub shared: bool,
+    pub libdir: Option<String>,
+    pub pointer_width: Option<u32>,
+    pub py_sys_config: Vec<String>,
+}
+
+impl InterpreterConfig {
+    /// Parse an [InterpreterConfig] from its `key = value` text representation.
+    pub fn parse(data: &str) -> Result<Self> {
+        let mut config = Self::default();
+
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("malformed interpreter config line: {}", line))?;
+            let key = key.trim();
+            let value = value.trim();
+
+            match key {
+                "version" => config.version = value.to_string(),
+                "implementation" => config.implementation = value.to_lowercase(),
+                "shared" => config.shared = value.parse().context("parsing shared")?,
+                "libdir" => config.libdir = Some(value.to_string()),
+                "pointer_width" => {
+                    config.pointer_width = Some(value.parse().context("parsing pointer_width")?)
+                }
+                "py_sys_config" => {
+                    config.py_sys_config = value.split(',').map(|v| v.trim().to_string()).collect()
+                }
+                _ => {}
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Parse an [InterpreterConfig] from a file on disk.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("reading interpreter config file {}", path.display()))?;
+
+        Self::parse(&data)
+    }
+}
+
 /// Create a static libpython from a Python distribution.
 ///
+/// `interpreter_config`, when present, describes the target interpreter's ABI
+/// directly rather than relying on host autodetection, enabling
+/// cross-compilation to targets without a runnable interpreter. When absent,
+/// existing autodetection behavior is unchanged.
+///
 /// Returns a vector of cargo: lines that can be printed in build scripts.
 #[allow(clippy::too_many_arguments)]
 pub fn link_libpython(
@@ -67,6 +304,7 @@ pub fn link_libpython(
     target_triple: &str,
     opt_level: &str,
     apple_sdk_info: Option<&AppleSdkInfo>,
+    interpreter_config: Option<&InterpreterConfig>,
 ) -> Result<LibpythonInfo> {
     let mut cargo_metadata: Vec<String> = Vec::new();
 
@@ -89,7 +327,11 @@ pub fn link_libpython(
         "deriving custom config.c from {} extension modules",
         context.init_functions.len()
     );
-    let config_c_source = make_config_c(&context.init_functions.iter().collect::<Vec<_>>());
+    let config_c_source = make_config_c(
+        &context.init_functions.iter().collect::<Vec<_>>(),
+        context.interpreter_kind,
+        context.abi3_minor,
+    );
     let config_c_path = out_dir.join("config.c");
     let config_c_temp_path = temp_dir_path.join("config.c");
 
@@ -196,9 +438,12 @@ pub fn link_libpython(
     // pulls it in, we'll get unresolved symbol errors when attempting to link
     // the final binary. Our solution to this is to always annotate
     // `clang_rt.<platform>` as a library dependency of our static libpython.
+    let mut prunable_search_candidates: Vec<PathBuf> = vec![out_dir.to_path_buf()];
+    let mut always_keep_search_paths: Vec<PathBuf> = context.library_search_paths.clone();
+
     if target_triple.ends_with("-apple-darwin") {
         if let Some(path) = macos_clang_search_path()? {
-            cargo_metadata.push(format!("cargo:rustc-link-search={}", path.display()));
+            prunable_search_candidates.push(path);
         }
 
         cargo_metadata.push("cargo:rustc-link-lib=clang_rt.osx".to_string());
@@ -219,27 +464,100 @@ pub fn link_libpython(
     //
     // Our current workaround is to produce a ``pythonXY.lib`` file. This satisfies
     // the requirement of ``python3-sys`` that a ``pythonXY.lib`` file exists.
+    //
+    // When building against the abi3 stable ABI, pyo3's ``ABI3_MAX_MINOR``-style
+    // version-agnostic linking expects a ``python3.lib`` stub instead, since the
+    // same binary is meant to keep working against any 3.X.Y the host distribution
+    // upgrades to. PyPy's runtime library has an entirely different base name.
+    let python_lib_name = runtime_library_name(context.interpreter_kind, context.abi3_minor);
 
-    warn!(logger, "compiling libpythonXY...");
-    build.compile("pythonXY");
-    warn!(logger, "libpythonXY created");
+    warn!(logger, "compiling lib{}...", python_lib_name);
+    build.compile(python_lib_name);
+    warn!(logger, "lib{} created", python_lib_name);
 
     let libpython_path = out_dir.join(if windows {
-        "pythonXY.lib"
+        format!("{}.lib", python_lib_name)
     } else {
-        "libpythonXY.a"
+        format!("lib{}.a", python_lib_name)
     });
 
-    cargo_metadata.push("cargo:rustc-link-lib=static=pythonXY".to_string());
-    cargo_metadata.push(format!(
-        "cargo:rustc-link-search=native={}",
-        out_dir.display()
-    ));
-
-    for path in &context.library_search_paths {
+    cargo_metadata.push(format!("cargo:rustc-link-lib=static={}", python_lib_name));
+
+    // `out_dir` and the macOS clang_rt directory are ours to reason about, so we
+    // canonicalize, deduplicate, and drop them unless they actually contain one
+    // of the libraries we just told rustc to link -- on some platforms unpruned
+    // duplicate/irrelevant search directories are enough to push the linker
+    // invocation past argument limits. `context.library_search_paths` comes from
+    // the distribution and is only deduplicated, not pruned: a directory there
+    // may hold a versioned shared library (e.g. `libssl.so.1.1`) with no
+    // unversioned name present, and incorrectly dropping it would break the link.
+    for path in minimize_link_search_paths(
+        &cargo_metadata,
+        &prunable_search_candidates,
+        &always_keep_search_paths,
+    ) {
         cargo_metadata.push(format!("cargo:rustc-link-search=native={}", path.display()));
     }
 
+    // When cross-compiling against a serialized interpreter config rather than
+    // the host's own Python, let it drive the parts of the link that would
+    // otherwise come from probing a runnable host interpreter.
+    if let Some(config) = interpreter_config {
+        if let Some(width) = config.pointer_width {
+            let target_width: u32 = if target_triple.starts_with("x86_64")
+                || target_triple.starts_with("aarch64")
+            {
+                64
+            } else {
+                32
+            };
+
+            if width != target_width {
+                warn!(
+                    logger,
+                    "interpreter config declares a {}-bit interpreter, but {} is {}-bit",
+                    width,
+                    target_triple,
+                    target_width
+                );
+            }
+        }
+
+        if let Some(libdir) = &config.libdir {
+            always_keep_search_paths.push(PathBuf::from(libdir));
+        }
+
+        // A `shared` target interpreter's libpython isn't one we build and embed
+        // ourselves -- it's the real, versioned shared library living in
+        // `libdir` on the target, and must be linked dynamically instead of
+        // using our locally-compiled static `pythonXY`/`pypy3-c` archive.
+        if config.shared {
+            let version_nodot = config.version.replace('.', "");
+            let dylib_name = match config.implementation.as_str() {
+                "pypy" => "pypy3-c".to_string(),
+                _ => format!("python{}", version_nodot),
+            };
+
+            let static_line = format!("cargo:rustc-link-lib=static={}", python_lib_name);
+            cargo_metadata.retain(|line| line != &static_line);
+            cargo_metadata.push(format!("cargo:rustc-link-lib=dylib={}", dylib_name));
+        }
+
+        // Re-emit, since `libdir` was appended after the main dedup pass above ran.
+        for path in
+            minimize_link_search_paths(&cargo_metadata, &prunable_search_candidates, &always_keep_search_paths)
+        {
+            let line = format!("cargo:rustc-link-search=native={}", path.display());
+            if !cargo_metadata.contains(&line) {
+                cargo_metadata.push(line);
+            }
+        }
+
+        for name in &config.py_sys_config {
+            cargo_metadata.push(format!("cargo:rustc-cfg=py_sys_config=\"{}\"", name));
+        }
+    }
+
     Ok(LibpythonInfo {
         libpython_path,
         libpyembeddedconfig_path,
@@ -268,3 +586,166 @@ fn macos_clang_search_path() -> Result<Option<PathBuf>> {
 
     Ok(None)
 }
+
+/// Derive candidate library filenames for a name passed to `rustc-link-lib`.
+fn candidate_library_filenames(name: &str) -> Vec<String> {
+    vec![
+        format!("lib{}.a", name),
+        format!("lib{}.so", name),
+        format!("lib{}.dylib", name),
+        format!("{}.lib", name),
+        format!("{}.dll", name),
+    ]
+}
+
+/// Returns `true` if `dir` appears to contain a file backing the `rustc-link-lib`
+/// name `lib_name`, accounting for versioned shared objects like
+/// `libssl.so.1.1` in addition to the canonical `lib{name}.so`-style names.
+fn directory_has_library(dir: &Path, lib_name: &str) -> bool {
+    if candidate_library_filenames(lib_name)
+        .iter()
+        .any(|filename| dir.join(filename).exists())
+    {
+        return true;
+    }
+
+    let versioned_prefix = format!("lib{}.so.", lib_name);
+
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries.filter_map(|e| e.ok()).any(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .map(|name| name.starts_with(&versioned_prefix))
+                    .unwrap_or(false)
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Deduplicate and prune a list of candidate `rustc-link-search` directories.
+///
+/// `cargo_metadata` is scanned for `cargo:rustc-link-lib=...` lines to
+/// determine which library names are actually referenced. `prunable` entries
+/// (our own out_dir and platform-specific search paths we derive ourselves,
+/// like the macOS clang_rt directory) are canonicalized, deduplicated, and
+/// dropped unless they contain one of those libraries. `always_keep` entries
+/// (distribution-provided `library_search_paths`, and any target `libdir` from
+/// a cross-compilation config) are only deduplicated -- we can't reliably
+/// predict every filename a distribution's libraries might use (e.g. `.so.N`
+/// suffixes with no unversioned symlink present at build time), so we trust
+/// the distribution's own judgment about what it needs linked.
+fn minimize_link_search_paths(
+    cargo_metadata: &[String],
+    prunable: &[PathBuf],
+    always_keep: &[PathBuf],
+) -> Vec<PathBuf> {
+    let lib_names: Vec<&str> = cargo_metadata
+        .iter()
+        .filter_map(|line| line.strip_prefix("cargo:rustc-link-lib="))
+        .map(|value| value.rsplit('=').next().unwrap_or(value))
+        .collect();
+
+    let mut seen = std::collections::HashSet::new();
+    let mut kept = Vec::new();
+
+    for candidate in always_keep {
+        let canonical = candidate.canonicalize().unwrap_or_else(|_| candidate.clone());
+
+        if seen.insert(canonical.clone()) {
+            kept.push(canonical);
+        }
+    }
+
+    for candidate in prunable {
+        let canonical = candidate.canonicalize().unwrap_or_else(|_| candidate.clone());
+
+        if !seen.insert(canonical.clone()) {
+            continue;
+        }
+
+        let is_referenced = lib_names
+            .iter()
+            .any(|name| directory_has_library(&canonical, name));
+
+        if is_referenced {
+            kept.push(canonical);
+        }
+    }
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn make_config_c_abi3_limited_api_define() {
+        let source = make_config_c::<&str>(&[], InterpreterKind::CPython, Some(8));
+        assert!(source.contains("#define Py_LIMITED_API 0x03080000"));
+
+        let source = make_config_c::<&str>(&[], InterpreterKind::CPython, Some(10));
+        assert!(source.contains("#define Py_LIMITED_API 0x030a0000"));
+    }
+
+    #[test]
+    fn minimize_link_search_paths_dedupes_and_filters_prunable() {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("libpython-test")
+            .tempdir()
+            .unwrap();
+
+        let has_lib = temp_dir.path().join("has_lib");
+        let empty = temp_dir.path().join("empty");
+        fs::create_dir_all(&has_lib).unwrap();
+        fs::create_dir_all(&empty).unwrap();
+        fs::write(has_lib.join("libfoo.a"), b"").unwrap();
+
+        let cargo_metadata = vec!["cargo:rustc-link-lib=static=foo".to_string()];
+        let prunable = vec![has_lib.clone(), has_lib.clone(), empty.clone()];
+
+        let kept = minimize_link_search_paths(&cargo_metadata, &prunable, &[]);
+
+        assert_eq!(kept, vec![has_lib.canonicalize().unwrap()]);
+    }
+
+    #[test]
+    fn minimize_link_search_paths_keeps_always_keep_unconditionally() {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("libpython-test")
+            .tempdir()
+            .unwrap();
+
+        // A directory containing only a versioned shared library with no
+        // unversioned name -- a prunable candidate would incorrectly drop this.
+        let versioned_only = temp_dir.path().join("versioned_only");
+        fs::create_dir_all(&versioned_only).unwrap();
+        fs::write(versioned_only.join("libssl.so.1.1"), b"").unwrap();
+
+        let cargo_metadata = vec!["cargo:rustc-link-lib=ssl".to_string()];
+        let always_keep = vec![versioned_only.clone(), versioned_only.clone()];
+
+        let kept = minimize_link_search_paths(&cargo_metadata, &[], &always_keep);
+
+        assert_eq!(kept, vec![versioned_only.canonicalize().unwrap()]);
+    }
+
+    #[test]
+    fn minimize_link_search_paths_matches_versioned_shared_objects_when_prunable() {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("libpython-test")
+            .tempdir()
+            .unwrap();
+
+        let versioned_only = temp_dir.path().join("versioned_only");
+        fs::create_dir_all(&versioned_only).unwrap();
+        fs::write(versioned_only.join("libssl.so.1.1"), b"").unwrap();
+
+        let cargo_metadata = vec!["cargo:rustc-link-lib=ssl".to_string()];
+        let kept = minimize_link_search_paths(&cargo_metadata, &[versioned_only.clone()], &[]);
+
+        assert_eq!(kept, vec![versioned_only.canonicalize().unwrap()]);
+    }
+}
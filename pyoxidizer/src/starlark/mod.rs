@@ -0,0 +1,20 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+PyOxidizer's Starlark dialect: value wrappers and global function registration.
+*/
+
+pub mod python_package_distribution_resource;
+
+use starlark::environment::Environment;
+
+/// Register this crate's Starlark global functions into `env`.
+///
+/// Each `*_module` function below is generated by a `starlark_module!` block
+/// in its respective submodule; this is the single place they're all wired
+/// into the global Starlark environment PyOxidizer configs evaluate against.
+pub fn register_global_functions(env: &mut Environment) {
+    python_package_distribution_resource::python_package_distribution_resource_module(env);
+}
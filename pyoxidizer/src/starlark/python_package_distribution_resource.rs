@@ -4,16 +4,56 @@
 
 use {
     super::python_resource::ResourceCollectionContext,
+    anyhow::Context,
     python_packaging::{
         resource::{PythonPackageDistributionResource, PythonResource},
         resource_collection::PythonResourceAddCollectionContext,
     },
-    starlark::values::{
-        error::{UnsupportedOperation, ValueError},
-        {Mutable, TypedValue, Value, ValueResult},
+    starlark::{
+        starlark_module,
+        values::{
+            error::{RuntimeError, UnsupportedOperation, ValueError},
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
+        },
     },
+    std::path::PathBuf,
 };
 
+/// A Starlark `Value` wrapper holding raw bytes.
+///
+/// This module can't assume the Starlark dialect in use has its own bytes
+/// type with a ready-made `Value` conversion, so resource data read via the
+/// `data` attribute is wrapped in this minimal, self-contained `TypedValue`
+/// instead.
+#[derive(Debug, Clone)]
+pub struct PythonPackageDistributionResourceDataValue(pub Vec<u8>);
+
+impl TypedValue for PythonPackageDistributionResourceDataValue {
+    type Holder = Mutable<PythonPackageDistributionResourceDataValue>;
+    const TYPE: &'static str = "bytes";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn to_str(&self) -> String {
+        format!("bytes(len={})", self.0.len())
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    fn length(&self) -> Result<i64, ValueError> {
+        Ok(self.0.len() as i64)
+    }
+}
+
 /// Starlark `Value` wrapper for `PythonPackageDistributionResource`.
 #[derive(Debug, Clone)]
 pub struct PythonPackageDistributionResourceValue {
@@ -74,7 +114,17 @@ impl TypedValue for PythonPackageDistributionResourceValue {
             "is_stdlib" => Value::from(false),
             "package" => Value::new(self.inner.package.clone()),
             "name" => Value::new(self.inner.name.clone()),
-            // TODO expose raw data
+            "data" => {
+                let data = self.inner.data.resolve().map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: format!("error resolving resource data: {}", e),
+                        label: "data".to_string(),
+                    })
+                })?;
+
+                Value::new(PythonPackageDistributionResourceDataValue(data))
+            }
             attr => {
                 return if self.add_collection_context_attrs().contains(&attr) {
                     self.get_attr_add_collection_context(attr)
@@ -96,7 +146,7 @@ impl TypedValue for PythonPackageDistributionResourceValue {
             "is_stdlib" => true,
             "package" => true,
             "name" => true,
-            // TODO expose raw data
+            "data" => true,
             attr => self.add_collection_context_attrs().contains(&attr),
         })
     }
@@ -104,4 +154,41 @@ impl TypedValue for PythonPackageDistributionResourceValue {
     fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
         self.set_attr_add_collection_context(attribute, value)
     }
-}
\ No newline at end of file
+}
+
+starlark_module! { python_package_distribution_resource_module =>
+    PythonPackageDistributionResource.write_to_path(env env, this, path: String) {
+        let resource = this.downcast_ref::<PythonPackageDistributionResourceValue>().ok_or(
+            ValueError::IncorrectParameterType,
+        )?;
+
+        let data = resource.inner.data.resolve().map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("error resolving resource data: {}", e),
+                label: "write_to_path".to_string(),
+            })
+        })?;
+
+        let dest_path = PathBuf::from(path);
+
+        (|| -> anyhow::Result<()> {
+            if let Some(parent) = dest_path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating directory {}", parent.display()))?;
+            }
+
+            std::fs::write(&dest_path, &data)
+                .with_context(|| format!("writing {}", dest_path.display()))
+        })()
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: e.to_string(),
+                label: "write_to_path".to_string(),
+            })
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+}
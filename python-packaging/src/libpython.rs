@@ -0,0 +1,67 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Data structures describing how to build a statically linked libpython.
+*/
+
+use {std::path::PathBuf, tugger_file_manifest::FileData};
+
+/// The Python interpreter implementation being embedded.
+///
+/// PyPy's embedded-interpreter layout differs enough from CPython's -- the
+/// built-in inittab, the runtime library's base name, and the set of required
+/// system/dynamic libraries are all different -- that consumers need to branch
+/// on it explicitly.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InterpreterKind {
+    CPython,
+    PyPy,
+}
+
+impl Default for InterpreterKind {
+    fn default() -> Self {
+        InterpreterKind::CPython
+    }
+}
+
+/// Describes how to build a statically linked libpython.
+#[derive(Clone, Debug, Default)]
+pub struct LibPythonBuildContext {
+    /// Extra compiler flags to use when building the config.c inittab object.
+    pub inittab_cflags: Option<Vec<String>>,
+
+    /// Extension module name to initialization function mappings to register
+    /// as built-ins in config.c's `_PyImport_Inittab`.
+    pub init_functions: Vec<(String, String)>,
+
+    /// Header files that need to be materialized for the config.c compile.
+    pub includes: Vec<(String, FileData)>,
+
+    /// Object files to link into libpython.
+    pub object_files: Vec<FileData>,
+
+    /// Apple frameworks that need to be linked.
+    pub frameworks: Vec<String>,
+
+    /// System libraries that need to be linked.
+    pub system_libraries: Vec<String>,
+
+    /// Dynamic libraries that need to be linked.
+    pub dynamic_libraries: Vec<String>,
+
+    /// Static libraries that need to be linked.
+    pub static_libraries: Vec<String>,
+
+    /// Additional library search paths to pass to the linker.
+    pub library_search_paths: Vec<PathBuf>,
+
+    /// The interpreter implementation being embedded.
+    pub interpreter_kind: InterpreterKind,
+
+    /// The minor version floor (e.g. `8` for 3.8) of the CPython stable ABI
+    /// to build the embedded interpreter against, if an abi3/limited-API
+    /// build was requested.
+    pub abi3_minor: Option<u8>,
+}